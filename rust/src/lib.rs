@@ -3,10 +3,15 @@
 // Include the auto-generated FFI bindings
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
 
+use std::collections::{BTreeMap, HashMap};
 use std::ffi::{CStr, CString};
 use std::ptr;
 use thiserror::Error;
 
+/// Sentinel value used in [`SimplifyOptions::with_input_shapes`] to leave an
+/// axis dynamic instead of pinning it to a concrete size.
+pub const DYNAMIC_DIM: i64 = -1;
+
 /// Error type for ONNX simplifier operations
 #[derive(Error, Debug)]
 pub enum OnnxSimError {
@@ -25,6 +30,9 @@ pub enum OnnxSimError {
     #[error("Internal error: {0}")]
     Internal(String),
 
+    #[error("Validation failed: {0}")]
+    ValidationFailed(String),
+
     #[error("Nul error: {0}")]
     NulError(#[from] std::ffi::NulError),
 }
@@ -38,6 +46,58 @@ impl From<OnnxSimError> for String {
 /// Result type for ONNX simplifier operations
 pub type Result<T> = std::result::Result<T, OnnxSimError>;
 
+/// An ONNX Runtime execution provider to use while running the graph for
+/// constant folding.
+///
+/// Providers are tried in the order supplied to
+/// [`SimplifyOptions::with_execution_providers`]; a CPU provider is always
+/// appended implicitly so folding still succeeds when a requested provider
+/// (e.g. CUDA) isn't available on the host.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecutionProvider {
+    /// CPU execution provider
+    Cpu,
+    /// CUDA execution provider
+    Cuda {
+        /// CUDA device ordinal
+        device_id: i32,
+    },
+    /// TensorRT execution provider
+    TensorRt {
+        /// CUDA device ordinal backing the TensorRT provider
+        device_id: i32,
+    },
+}
+
+impl ExecutionProvider {
+    fn kind(&self) -> i32 {
+        match self {
+            ExecutionProvider::Cpu => 0,
+            ExecutionProvider::Cuda { .. } => 1,
+            ExecutionProvider::TensorRt { .. } => 2,
+        }
+    }
+
+    fn device_id(&self) -> i32 {
+        match self {
+            ExecutionProvider::Cpu => -1,
+            ExecutionProvider::Cuda { device_id } | ExecutionProvider::TensorRt { device_id } => {
+                *device_id
+            }
+        }
+    }
+}
+
+/// Opt-in correctness-validation configuration: run `num_samples` random
+/// inputs through the original and simplified graphs and compare outputs
+/// element-wise within the given tolerances.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ValidationConfig {
+    pub num_samples: usize,
+    pub rtol: f32,
+    pub atol: f32,
+}
+
 /// Configuration options for model simplification
 #[derive(Debug, Clone, Default)]
 pub struct SimplifyOptions {
@@ -52,6 +112,31 @@ pub struct SimplifyOptions {
 
     /// Tensor size threshold for optimization
     pub tensor_size_threshold: usize,
+
+    /// Ordered list of execution providers to run constant folding with.
+    /// Empty means CPU-only.
+    pub execution_providers: Vec<ExecutionProvider>,
+
+    /// Concrete shapes to substitute for named graph inputs before shape
+    /// inference and constant folding run. Use [`DYNAMIC_DIM`] for an axis
+    /// that should remain dynamic.
+    pub input_shapes: Option<HashMap<String, Vec<i64>>>,
+
+    /// When set, validate the simplified model against the original by
+    /// running random inputs through both and comparing outputs.
+    pub validation: Option<ValidationConfig>,
+
+    /// Minimum initializer size, in bytes, above which the saved model
+    /// offloads the initializer to an external `<name>.data` file instead of
+    /// embedding it in the model protobuf. `None` (the default) disables
+    /// external-data offloading and keeps the model fully self-contained.
+    /// Only takes effect for [`simplify_file`].
+    pub external_data_threshold: Option<usize>,
+
+    /// Directory external-data files are written to on save. Defaults to
+    /// the output path's parent directory when unset. Only takes effect
+    /// for [`simplify_file`].
+    pub external_data_dir: Option<std::path::PathBuf>,
 }
 
 impl SimplifyOptions {
@@ -78,6 +163,344 @@ impl SimplifyOptions {
         self.tensor_size_threshold = threshold;
         self
     }
+
+    /// Set the execution providers used to run the graph during constant
+    /// folding, in priority order. A CPU provider is always appended as a
+    /// fallback if not already present.
+    pub fn with_execution_providers(mut self, providers: Vec<ExecutionProvider>) -> Self {
+        self.execution_providers = providers;
+        self
+    }
+
+    /// Override the shape of one or more named graph inputs. Pass
+    /// [`DYNAMIC_DIM`] for any axis that should remain symbolic.
+    ///
+    /// Every `simplify_*` entry point validates these names against the
+    /// model's declared inputs before crossing the FFI boundary, returning
+    /// [`OnnxSimError::InvalidArgument`] for any name that isn't one of
+    /// them. See [`model_input_names`]. The check is skipped when the model
+    /// can't be read or parsed far enough to list its inputs; the FFI call
+    /// then reports its own error.
+    pub fn with_input_shapes(mut self, input_shapes: HashMap<String, Vec<i64>>) -> Self {
+        self.input_shapes = Some(input_shapes);
+        self
+    }
+
+    /// Enable correctness validation: run `num_samples` random inputs
+    /// through the original and simplified models and compare outputs
+    /// within `rtol`/`atol`, failing with
+    /// [`OnnxSimError::ValidationFailed`] on the first mismatch.
+    pub fn with_validation(mut self, num_samples: usize, rtol: f32, atol: f32) -> Self {
+        self.validation = Some(ValidationConfig {
+            num_samples,
+            rtol,
+            atol,
+        });
+        self
+    }
+
+    /// Flatten `execution_providers` into parallel kind/device-id arrays,
+    /// appending a CPU fallback if one isn't already present anywhere in
+    /// the list.
+    fn execution_provider_arrays(&self) -> (Vec<i32>, Vec<i32>) {
+        let mut kinds: Vec<i32> = self.execution_providers.iter().map(|p| p.kind()).collect();
+        let mut device_ids: Vec<i32> = self
+            .execution_providers
+            .iter()
+            .map(|p| p.device_id())
+            .collect();
+
+        let has_cpu = self
+            .execution_providers
+            .iter()
+            .any(|p| matches!(p, ExecutionProvider::Cpu));
+        if !has_cpu {
+            kinds.push(ExecutionProvider::Cpu.kind());
+            device_ids.push(ExecutionProvider::Cpu.device_id());
+        }
+
+        (kinds, device_ids)
+    }
+
+    /// Enable external-data offloading: initializers above `threshold`
+    /// bytes are written to an external `<name>.data` file next to the
+    /// output when saving via [`simplify_file`], instead of being embedded
+    /// in the model protobuf.
+    pub fn with_external_data_threshold(mut self, threshold: usize) -> Self {
+        self.external_data_threshold = Some(threshold);
+        self
+    }
+
+    /// Override the directory external-data files are written to (defaults
+    /// to the output path's parent directory).
+    pub fn with_external_data_dir<P: Into<std::path::PathBuf>>(mut self, dir: P) -> Self {
+        self.external_data_dir = Some(dir.into());
+        self
+    }
+
+    /// Flatten `external_data_threshold` into the `(enabled, threshold)`
+    /// pair the FFI boundary expects.
+    fn external_data_params(&self) -> (i32, usize) {
+        match self.external_data_threshold {
+            Some(threshold) => (1, threshold),
+            None => (0, 0),
+        }
+    }
+
+    /// Flatten `validation` into the `(enabled, num_samples, rtol, atol)`
+    /// tuple the FFI boundary expects.
+    fn validation_params(&self) -> (i32, usize, f32, f32) {
+        match self.validation {
+            Some(config) => (1, config.num_samples, config.rtol, config.atol),
+            None => (0, 0, 0.0, 0.0),
+        }
+    }
+}
+
+/// Flatten an optional input-shape map into the parallel arrays the FFI
+/// boundary expects: one name per input, all dims concatenated, and the
+/// per-input dim count needed to split them back apart on the C++ side.
+///
+/// Name validity is checked separately by [`validate_input_shape_names`]
+/// before this is called; this function only lowers whatever map it's
+/// given.
+fn input_shape_arrays(
+    input_shapes: &Option<HashMap<String, Vec<i64>>>,
+) -> Result<(Vec<CString>, Vec<i64>, Vec<usize>)> {
+    let mut names = Vec::new();
+    let mut dims = Vec::new();
+    let mut dim_counts = Vec::new();
+
+    if let Some(input_shapes) = input_shapes {
+        for (name, shape) in input_shapes {
+            names.push(
+                CString::new(name.as_str())
+                    .map_err(|e| OnnxSimError::InvalidArgument(e.to_string()))?,
+            );
+            dim_counts.push(shape.len());
+            dims.extend_from_slice(shape);
+        }
+    }
+
+    Ok((names, dims, dim_counts))
+}
+
+/// Read a protobuf varint starting at `*pos`, advancing `*pos` past it.
+fn read_varint(buf: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut result: u64 = 0;
+    let mut shift = 0;
+    loop {
+        let byte = *buf.get(*pos)?;
+        *pos += 1;
+        result |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(result);
+        }
+        shift += 7;
+        if shift >= 64 {
+            return None;
+        }
+    }
+}
+
+/// Advance `*pos` past the value of a field with the given wire type,
+/// without interpreting it.
+fn skip_field_value(buf: &[u8], pos: &mut usize, wire_type: u64) -> Option<()> {
+    match wire_type {
+        0 => {
+            read_varint(buf, pos)?;
+        }
+        1 => *pos += 8,
+        2 => {
+            let len = read_varint(buf, pos)? as usize;
+            *pos += len;
+        }
+        5 => *pos += 4,
+        _ => return None,
+    }
+    if *pos > buf.len() {
+        return None;
+    }
+    Some(())
+}
+
+/// Collect the length-delimited payload of every top-level occurrence of
+/// `field_number` in a protobuf message `buf`. Used to walk just enough of
+/// a serialized ONNX `ModelProto` to find its declared input names,
+/// without depending on a full protobuf/ONNX parser.
+fn length_delimited_fields(buf: &[u8], field_number: u64) -> Vec<&[u8]> {
+    let mut matches = Vec::new();
+    let mut pos = 0;
+
+    while pos < buf.len() {
+        let tag = match read_varint(buf, &mut pos) {
+            Some(tag) => tag,
+            None => break,
+        };
+        let wire_type = tag & 0x7;
+
+        if wire_type == 2 {
+            let len = match read_varint(buf, &mut pos) {
+                Some(len) => len as usize,
+                None => break,
+            };
+            if pos + len > buf.len() {
+                break;
+            }
+            if tag >> 3 == field_number {
+                matches.push(&buf[pos..pos + len]);
+            }
+            pos += len;
+        } else if skip_field_value(buf, &mut pos, wire_type).is_none() {
+            break;
+        }
+    }
+
+    matches
+}
+
+/// Best-effort extraction of the declared input names from a serialized
+/// ONNX `ModelProto` (`model.graph.input[].name`, fields 7/11/1 per
+/// `onnx.proto`), used to validate [`SimplifyOptions::input_shapes`] in
+/// Rust before crossing the FFI boundary. Returns `None` if `model_bytes`
+/// doesn't look like a well-formed model; callers then skip the name
+/// check and let the C++ wrapper's own parse step surface a clearer
+/// parse error instead.
+fn model_input_names(model_bytes: &[u8]) -> Option<Vec<String>> {
+    let graph = length_delimited_fields(model_bytes, 7).into_iter().next()?;
+    let inputs = length_delimited_fields(graph, 11);
+
+    let mut names = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let name_bytes = length_delimited_fields(input, 1).into_iter().next()?;
+        names.push(String::from_utf8(name_bytes.to_vec()).ok()?);
+    }
+    Some(names)
+}
+
+/// Validate that every name in `input_shapes` matches a declared input of
+/// `model_bytes`, returning [`OnnxSimError::InvalidArgument`] for the
+/// first one that doesn't.
+///
+/// If `model_bytes` can't be parsed well enough to list its inputs (e.g. a
+/// malformed model, which the FFI call below will reject anyway), this
+/// skips the check rather than reporting a misleading name error.
+fn validate_input_shape_names(
+    model_bytes: &[u8],
+    input_shapes: &Option<HashMap<String, Vec<i64>>>,
+) -> Result<()> {
+    let Some(input_shapes) = input_shapes else {
+        return Ok(());
+    };
+    if input_shapes.is_empty() {
+        return Ok(());
+    }
+    let Some(declared) = model_input_names(model_bytes) else {
+        return Ok(());
+    };
+
+    let declared: std::collections::HashSet<&str> = declared.iter().map(String::as_str).collect();
+    for name in input_shapes.keys() {
+        if !declared.contains(name.as_str()) {
+            return Err(OnnxSimError::InvalidArgument(format!(
+                "input_shapes has no matching model input named '{name}'"
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Return a raw pointer suitable for an FFI `const char**` argument: null
+/// when `ptrs` is empty (some C++ wrappers treat a non-null empty array
+/// differently from "not supplied"), otherwise a pointer to its storage.
+fn ptr_or_null(ptrs: &[*const i8]) -> *mut *const i8 {
+    if ptrs.is_empty() {
+        ptr::null_mut()
+    } else {
+        ptrs.as_ptr() as *mut *const i8
+    }
+}
+
+/// Owning lowering of a [`SimplifyOptions`] into the buffers every
+/// `simplify_*` FFI entry point needs, so the CString/array prep that's
+/// common to all of them (skip-optimizer names, execution providers,
+/// input-shape overrides, validation config) only has to be written once.
+/// Each entry point still builds its own call since their signatures
+/// diverge (byte buffers vs. paths, extra report/out-params, etc.).
+struct PreparedOptions {
+    skip_optimizers: Vec<CString>,
+    ep_kinds: Vec<i32>,
+    ep_device_ids: Vec<i32>,
+    input_shape_names: Vec<CString>,
+    input_shape_dims: Vec<i64>,
+    input_shape_dim_counts: Vec<usize>,
+    validation_enabled: i32,
+    validation_num_samples: usize,
+    validation_rtol: f32,
+    validation_atol: f32,
+}
+
+impl PreparedOptions {
+    fn new(options: &SimplifyOptions) -> Result<Self> {
+        let skip_optimizers: Result<Vec<CString>> = options
+            .skip_optimizers
+            .iter()
+            .flatten()
+            .map(|s| CString::new(s.as_str()).map_err(|e| OnnxSimError::InvalidArgument(e.to_string())))
+            .collect();
+
+        let (ep_kinds, ep_device_ids) = options.execution_provider_arrays();
+        let (input_shape_names, input_shape_dims, input_shape_dim_counts) =
+            input_shape_arrays(&options.input_shapes)?;
+        let (validation_enabled, validation_num_samples, validation_rtol, validation_atol) =
+            options.validation_params();
+
+        Ok(Self {
+            skip_optimizers: skip_optimizers?,
+            ep_kinds,
+            ep_device_ids,
+            input_shape_names,
+            input_shape_dims,
+            input_shape_dim_counts,
+            validation_enabled,
+            validation_num_samples,
+            validation_rtol,
+            validation_atol,
+        })
+    }
+
+    fn skip_optimizers_ptrs(&self) -> Vec<*const i8> {
+        self.skip_optimizers.iter().map(|s| s.as_ptr()).collect()
+    }
+
+    fn input_shape_name_ptrs(&self) -> Vec<*const i8> {
+        self.input_shape_names.iter().map(|s| s.as_ptr()).collect()
+    }
+}
+
+/// Read the last FFI error message and map a non-success status code to
+/// the corresponding [`OnnxSimError`] variant. Shared by every `simplify_*`
+/// entry point so the mapping only has to stay in sync with
+/// `onnxsim_ffi.h` in one place.
+unsafe fn map_ffi_error(result: onnxsim_error_t) -> OnnxSimError {
+    let error_ptr = onnxsim_get_last_error();
+    let error_msg = if error_ptr.is_null() {
+        String::from("Unknown error")
+    } else {
+        CStr::from_ptr(error_ptr).to_string_lossy().into_owned()
+    };
+
+    match result {
+        onnxsim_error_t_ONNXSIM_ERROR_INVALID_ARGUMENT => OnnxSimError::InvalidArgument(error_msg),
+        onnxsim_error_t_ONNXSIM_ERROR_PARSE_FAILED => OnnxSimError::ParseFailed(error_msg),
+        onnxsim_error_t_ONNXSIM_ERROR_SERIALIZE_FAILED => OnnxSimError::SerializeFailed(error_msg),
+        onnxsim_error_t_ONNXSIM_ERROR_SIMPLIFICATION_FAILED => {
+            OnnxSimError::SimplificationFailed(error_msg)
+        }
+        onnxsim_error_t_ONNXSIM_ERROR_VALIDATION_FAILED => OnnxSimError::ValidationFailed(error_msg),
+        _ => OnnxSimError::Internal(error_msg),
+    }
 }
 
 /// Initialize the ONNX environment
@@ -112,66 +535,152 @@ pub fn init_env() {
 /// ```
 pub fn simplify_bytes(model_bytes: &[u8], options: SimplifyOptions) -> Result<Vec<u8>> {
     init_env();
+    validate_input_shape_names(model_bytes, &options.input_shapes)?;
 
-    // Prepare skip optimizers
-    let skip_optimizers = options.skip_optimizers.unwrap_or_default();
-    let skip_optimizers_cstrings: Result<Vec<CString>> = skip_optimizers
-        .iter()
-        .map(|s| CString::new(s.as_str()).map_err(|e| OnnxSimError::InvalidArgument(e.to_string())))
-        .collect();
-    let skip_optimizers_cstrings = skip_optimizers_cstrings?;
+    let prepared = PreparedOptions::new(&options)?;
+    let skip_optimizers_ptrs = prepared.skip_optimizers_ptrs();
+    let input_shape_name_ptrs = prepared.input_shape_name_ptrs();
 
-    let skip_optimizers_ptrs: Vec<*const i8> = skip_optimizers_cstrings
-        .iter()
-        .map(|s| s.as_ptr())
-        .collect();
+    let mut out_bytes: *mut u8 = ptr::null_mut();
+    let mut out_bytes_len: usize = 0;
 
-    let skip_optimizers_ptr: *mut *const i8 = if skip_optimizers_ptrs.is_empty() {
-        ptr::null_mut()
-    } else {
-        skip_optimizers_ptrs.as_ptr() as *mut *const i8
+    let result = unsafe {
+        onnxsim_simplify_bytes(
+            model_bytes.as_ptr(),
+            model_bytes.len(),
+            ptr_or_null(&skip_optimizers_ptrs),
+            skip_optimizers_ptrs.len(),
+            options.constant_folding as i32,
+            options.shape_inference as i32,
+            options.tensor_size_threshold,
+            prepared.ep_kinds.as_ptr(),
+            prepared.ep_device_ids.as_ptr(),
+            prepared.ep_kinds.len(),
+            input_shape_name_ptrs.as_ptr(),
+            prepared.input_shape_dims.as_ptr(),
+            prepared.input_shape_dim_counts.as_ptr(),
+            input_shape_name_ptrs.len(),
+            prepared.validation_enabled,
+            prepared.validation_num_samples,
+            prepared.validation_rtol,
+            prepared.validation_atol,
+            &mut out_bytes,
+            &mut out_bytes_len,
+        )
+    };
+
+    if result != onnxsim_error_t_ONNXSIM_SUCCESS {
+        return Err(unsafe { map_ffi_error(result) });
+    }
+
+    // Copy the output bytes
+    let output = unsafe {
+        if out_bytes.is_null() || out_bytes_len == 0 {
+            return Err(OnnxSimError::Internal("Empty output".to_string()));
+        }
+        std::slice::from_raw_parts(out_bytes, out_bytes_len).to_vec()
     };
 
+    // Free the allocated memory
+    unsafe {
+        onnxsim_free_string(out_bytes as *mut _);
+    }
+
+    Ok(output)
+}
+
+/// Before/after summary of a simplification run, returned by
+/// [`simplify_bytes_with_report`].
+#[derive(Debug, Clone, Default)]
+pub struct SimplifyReport {
+    /// Node count per op type, keyed by op type name, as `(before, after)`.
+    pub node_counts_by_type: BTreeMap<String, (usize, usize)>,
+
+    /// Total node count as `(before, after)`.
+    pub total_nodes: (usize, usize),
+
+    /// Total initializer count as `(before, after)`.
+    pub total_initializers: (usize, usize),
+
+    /// Serialized model size in bytes as `(before, after)`.
+    pub model_size_bytes: (usize, usize),
+}
+
+/// Simplify an ONNX model from bytes, returning a [`SimplifyReport`]
+/// alongside the simplified model so callers can assert that simplification
+/// actually reduced the graph.
+///
+/// # Arguments
+///
+/// * `model_bytes` - The serialized model protobuf bytes
+/// * `options` - Simplification options
+///
+/// # Returns
+///
+/// The simplified model as bytes, together with a before/after report.
+pub fn simplify_bytes_with_report(
+    model_bytes: &[u8],
+    options: SimplifyOptions,
+) -> Result<(Vec<u8>, SimplifyReport)> {
+    init_env();
+    validate_input_shape_names(model_bytes, &options.input_shapes)?;
+
+    let prepared = PreparedOptions::new(&options)?;
+    let skip_optimizers_ptrs = prepared.skip_optimizers_ptrs();
+    let input_shape_name_ptrs = prepared.input_shape_name_ptrs();
+
     let mut out_bytes: *mut u8 = ptr::null_mut();
     let mut out_bytes_len: usize = 0;
 
+    let mut op_type_names: *mut *mut i8 = ptr::null_mut();
+    let mut op_type_counts_before: *mut usize = ptr::null_mut();
+    let mut op_type_counts_after: *mut usize = ptr::null_mut();
+    let mut op_type_count: usize = 0;
+
+    let mut total_nodes_before: usize = 0;
+    let mut total_nodes_after: usize = 0;
+    let mut total_initializers_before: usize = 0;
+    let mut total_initializers_after: usize = 0;
+    let mut model_size_before: usize = 0;
+    let mut model_size_after: usize = 0;
+
     let result = unsafe {
-        onnxsim_simplify_bytes(
+        onnxsim_simplify_bytes_with_report(
             model_bytes.as_ptr(),
             model_bytes.len(),
-            skip_optimizers_ptr,
+            ptr_or_null(&skip_optimizers_ptrs),
             skip_optimizers_ptrs.len(),
             options.constant_folding as i32,
             options.shape_inference as i32,
             options.tensor_size_threshold,
+            prepared.ep_kinds.as_ptr(),
+            prepared.ep_device_ids.as_ptr(),
+            prepared.ep_kinds.len(),
+            input_shape_name_ptrs.as_ptr(),
+            prepared.input_shape_dims.as_ptr(),
+            prepared.input_shape_dim_counts.as_ptr(),
+            input_shape_name_ptrs.len(),
+            prepared.validation_enabled,
+            prepared.validation_num_samples,
+            prepared.validation_rtol,
+            prepared.validation_atol,
             &mut out_bytes,
             &mut out_bytes_len,
+            &mut op_type_names,
+            &mut op_type_counts_before,
+            &mut op_type_counts_after,
+            &mut op_type_count,
+            &mut total_nodes_before,
+            &mut total_nodes_after,
+            &mut total_initializers_before,
+            &mut total_initializers_after,
+            &mut model_size_before,
+            &mut model_size_after,
         )
     };
 
     if result != onnxsim_error_t_ONNXSIM_SUCCESS {
-        let error_msg = unsafe {
-            let error_ptr = onnxsim_get_last_error();
-            if error_ptr.is_null() {
-                String::from("Unknown error")
-            } else {
-                CStr::from_ptr(error_ptr).to_string_lossy().into_owned()
-            }
-        };
-
-        return Err(match result {
-            onnxsim_error_t_ONNXSIM_ERROR_INVALID_ARGUMENT => {
-                OnnxSimError::InvalidArgument(error_msg)
-            }
-            onnxsim_error_t_ONNXSIM_ERROR_PARSE_FAILED => OnnxSimError::ParseFailed(error_msg),
-            onnxsim_error_t_ONNXSIM_ERROR_SERIALIZE_FAILED => {
-                OnnxSimError::SerializeFailed(error_msg)
-            }
-            onnxsim_error_t_ONNXSIM_ERROR_SIMPLIFICATION_FAILED => {
-                OnnxSimError::SimplificationFailed(error_msg)
-            }
-            _ => OnnxSimError::Internal(error_msg),
-        });
+        return Err(unsafe { map_ffi_error(result) });
     }
 
     // Copy the output bytes
@@ -182,12 +691,38 @@ pub fn simplify_bytes(model_bytes: &[u8], options: SimplifyOptions) -> Result<Ve
         std::slice::from_raw_parts(out_bytes, out_bytes_len).to_vec()
     };
 
+    // Copy the op-type histogram
+    let mut node_counts_by_type = BTreeMap::new();
+    unsafe {
+        for i in 0..op_type_count {
+            let name = CStr::from_ptr(*op_type_names.add(i))
+                .to_string_lossy()
+                .into_owned();
+            let before = *op_type_counts_before.add(i);
+            let after = *op_type_counts_after.add(i);
+            node_counts_by_type.insert(name, (before, after));
+        }
+    }
+
+    let report = SimplifyReport {
+        node_counts_by_type,
+        total_nodes: (total_nodes_before, total_nodes_after),
+        total_initializers: (total_initializers_before, total_initializers_after),
+        model_size_bytes: (model_size_before, model_size_after),
+    };
+
     // Free the allocated memory
     unsafe {
         onnxsim_free_string(out_bytes as *mut _);
+        onnxsim_free_report(
+            op_type_names,
+            op_type_counts_before,
+            op_type_counts_after,
+            op_type_count,
+        );
     }
 
-    Ok(output)
+    Ok((output, report))
 }
 
 /// Simplify an ONNX model from file path
@@ -213,6 +748,16 @@ pub fn simplify_file<P: AsRef<std::path::Path>>(
 ) -> Result<()> {
     init_env();
 
+    if options
+        .input_shapes
+        .as_ref()
+        .is_some_and(|shapes| !shapes.is_empty())
+    {
+        if let Ok(model_bytes) = std::fs::read(in_path.as_ref()) {
+            validate_input_shape_names(&model_bytes, &options.input_shapes)?;
+        }
+    }
+
     let in_path_str = in_path
         .as_ref()
         .to_str()
@@ -225,61 +770,174 @@ pub fn simplify_file<P: AsRef<std::path::Path>>(
         .ok_or_else(|| OnnxSimError::InvalidArgument("Invalid UTF-8 in output path".to_string()))?;
     let out_path_cstring = CString::new(out_path_str)?;
 
-    // Prepare skip optimizers
-    let skip_optimizers = options.skip_optimizers.unwrap_or_default();
-    let skip_optimizers_cstrings: Result<Vec<CString>> = skip_optimizers
-        .iter()
-        .map(|s| CString::new(s.as_str()).map_err(|e| OnnxSimError::InvalidArgument(e.to_string())))
-        .collect();
-    let skip_optimizers_cstrings = skip_optimizers_cstrings?;
+    let external_data_dir_cstring = options
+        .external_data_dir
+        .as_ref()
+        .map(|dir| {
+            let dir_str = dir.to_str().ok_or_else(|| {
+                OnnxSimError::InvalidArgument("Invalid UTF-8 in external data dir".to_string())
+            })?;
+            CString::new(dir_str).map_err(OnnxSimError::from)
+        })
+        .transpose()?;
+    let external_data_dir_ptr = external_data_dir_cstring
+        .as_ref()
+        .map_or(ptr::null(), |s| s.as_ptr());
 
-    let skip_optimizers_ptrs: Vec<*const i8> = skip_optimizers_cstrings
-        .iter()
-        .map(|s| s.as_ptr())
-        .collect();
-
-    let skip_optimizers_ptr: *mut *const i8 = if skip_optimizers_ptrs.is_empty() {
-        ptr::null_mut()
-    } else {
-        skip_optimizers_ptrs.as_ptr() as *mut *const i8
-    };
+    let prepared = PreparedOptions::new(&options)?;
+    let skip_optimizers_ptrs = prepared.skip_optimizers_ptrs();
+    let input_shape_name_ptrs = prepared.input_shape_name_ptrs();
+    let (external_data_enabled, external_data_threshold) = options.external_data_params();
 
     let result = unsafe {
         onnxsim_simplify_file(
             in_path_cstring.as_ptr(),
             out_path_cstring.as_ptr(),
-            skip_optimizers_ptr,
+            ptr_or_null(&skip_optimizers_ptrs),
             skip_optimizers_ptrs.len(),
             options.constant_folding as i32,
             options.shape_inference as i32,
             options.tensor_size_threshold,
+            prepared.ep_kinds.as_ptr(),
+            prepared.ep_device_ids.as_ptr(),
+            prepared.ep_kinds.len(),
+            input_shape_name_ptrs.as_ptr(),
+            prepared.input_shape_dims.as_ptr(),
+            prepared.input_shape_dim_counts.as_ptr(),
+            input_shape_name_ptrs.len(),
+            prepared.validation_enabled,
+            prepared.validation_num_samples,
+            prepared.validation_rtol,
+            prepared.validation_atol,
+            external_data_enabled,
+            external_data_threshold,
+            external_data_dir_ptr,
         )
     };
 
     if result != onnxsim_error_t_ONNXSIM_SUCCESS {
-        let error_msg = unsafe {
-            let error_ptr = onnxsim_get_last_error();
-            if error_ptr.is_null() {
-                String::from("Unknown error")
-            } else {
-                CStr::from_ptr(error_ptr).to_string_lossy().into_owned()
-            }
-        };
-
-        return Err(match result {
-            onnxsim_error_t_ONNXSIM_ERROR_INVALID_ARGUMENT => {
-                OnnxSimError::InvalidArgument(error_msg)
-            }
-            onnxsim_error_t_ONNXSIM_ERROR_PARSE_FAILED => OnnxSimError::ParseFailed(error_msg),
-            onnxsim_error_t_ONNXSIM_ERROR_SERIALIZE_FAILED => {
-                OnnxSimError::SerializeFailed(error_msg)
-            }
-            onnxsim_error_t_ONNXSIM_ERROR_SIMPLIFICATION_FAILED => {
-                OnnxSimError::SimplificationFailed(error_msg)
-            }
-            _ => OnnxSimError::Internal(error_msg),
-        });
+        return Err(unsafe { map_ffi_error(result) });
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn execution_provider_arrays_appends_cpu_fallback_when_absent() {
+        let options =
+            SimplifyOptions::new().with_execution_providers(vec![ExecutionProvider::Cuda {
+                device_id: 0,
+            }]);
+
+        let (kinds, device_ids) = options.execution_provider_arrays();
+
+        assert_eq!(kinds, vec![1, 0]);
+        assert_eq!(device_ids, vec![0, -1]);
+    }
+
+    #[test]
+    fn execution_provider_arrays_does_not_duplicate_cpu() {
+        let options = SimplifyOptions::new().with_execution_providers(vec![
+            ExecutionProvider::Cpu,
+            ExecutionProvider::Cuda { device_id: 0 },
+        ]);
+
+        let (kinds, device_ids) = options.execution_provider_arrays();
+
+        assert_eq!(kinds, vec![0, 1]);
+        assert_eq!(device_ids, vec![-1, 0]);
+    }
+
+    #[test]
+    fn input_shape_arrays_flattens_dims_and_records_per_input_lengths() {
+        let mut input_shapes = HashMap::new();
+        input_shapes.insert("input".to_string(), vec![1, 3, 224, 224]);
+
+        let (names, dims, dim_counts) = input_shape_arrays(&Some(input_shapes)).unwrap();
+
+        assert_eq!(names, vec![CString::new("input").unwrap()]);
+        assert_eq!(dims, vec![1, 3, 224, 224]);
+        assert_eq!(dim_counts, vec![4]);
+    }
+
+    #[test]
+    fn input_shape_arrays_is_empty_for_none() {
+        let (names, dims, dim_counts) = input_shape_arrays(&None).unwrap();
+
+        assert!(names.is_empty());
+        assert!(dims.is_empty());
+        assert!(dim_counts.is_empty());
+    }
+
+    /// Build a minimal serialized `ModelProto` containing only
+    /// `graph.input[].name` for the given names, for testing
+    /// [`model_input_names`] and [`validate_input_shape_names`] without a
+    /// real protobuf encoder.
+    fn fake_model_bytes(input_names: &[&str]) -> Vec<u8> {
+        fn length_delimited(field_number: u8, payload: &[u8]) -> Vec<u8> {
+            let mut out = vec![(field_number << 3) | 2];
+            out.push(payload.len() as u8);
+            out.extend_from_slice(payload);
+            out
+        }
+
+        let graph_bytes: Vec<u8> = input_names
+            .iter()
+            .flat_map(|name| {
+                let name_field = length_delimited(1, name.as_bytes());
+                length_delimited(11, &name_field)
+            })
+            .collect();
+
+        length_delimited(7, &graph_bytes)
+    }
+
+    #[test]
+    fn model_input_names_reads_declared_graph_inputs() {
+        let model_bytes = fake_model_bytes(&["input", "sequence_length"]);
+
+        let names = model_input_names(&model_bytes).unwrap();
+
+        assert_eq!(names, vec!["input".to_string(), "sequence_length".to_string()]);
+    }
+
+    #[test]
+    fn model_input_names_is_none_for_unparseable_bytes() {
+        assert_eq!(model_input_names(b"not a protobuf model"), None);
+    }
+
+    #[test]
+    fn validate_input_shape_names_accepts_declared_inputs() {
+        let model_bytes = fake_model_bytes(&["input"]);
+        let mut input_shapes = HashMap::new();
+        input_shapes.insert("input".to_string(), vec![1, 3, 224, 224]);
+
+        assert!(validate_input_shape_names(&model_bytes, &Some(input_shapes)).is_ok());
+    }
+
+    #[test]
+    fn validate_input_shape_names_rejects_unknown_input() {
+        let model_bytes = fake_model_bytes(&["input"]);
+        let mut input_shapes = HashMap::new();
+        input_shapes.insert("not_an_input".to_string(), vec![1]);
+
+        let result = validate_input_shape_names(&model_bytes, &Some(input_shapes));
+
+        match result {
+            Err(OnnxSimError::InvalidArgument(_)) => (),
+            _ => panic!("Expected InvalidArgument error"),
+        }
+    }
+
+    #[test]
+    fn validate_input_shape_names_skips_check_when_model_is_unparseable() {
+        let mut input_shapes = HashMap::new();
+        input_shapes.insert("anything".to_string(), vec![1]);
+
+        assert!(validate_input_shape_names(b"not a protobuf model", &Some(input_shapes)).is_ok());
+    }
+}
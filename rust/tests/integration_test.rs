@@ -1,4 +1,9 @@
-use onnxsim::{simplify_bytes, simplify_file, SimplifyOptions};
+use std::collections::HashMap;
+
+use onnxsim::{
+    simplify_bytes, simplify_bytes_with_report, simplify_file, ExecutionProvider, SimplifyOptions,
+    DYNAMIC_DIM,
+};
 
 #[test]
 fn test_init_env() {
@@ -33,6 +38,105 @@ fn test_simplify_bytes_with_invalid_input() {
     }
 }
 
+#[test]
+fn test_simplify_options_with_execution_providers() {
+    let options = SimplifyOptions::new().with_execution_providers(vec![
+        ExecutionProvider::Cuda { device_id: 0 },
+        ExecutionProvider::Cpu,
+    ]);
+
+    assert_eq!(
+        options.execution_providers,
+        vec![ExecutionProvider::Cuda { device_id: 0 }, ExecutionProvider::Cpu]
+    );
+}
+
+#[test]
+fn test_simplify_options_with_input_shapes() {
+    let mut input_shapes = HashMap::new();
+    input_shapes.insert("input".to_string(), vec![1, 3, 224, 224]);
+    input_shapes.insert("sequence_length".to_string(), vec![DYNAMIC_DIM, 768]);
+
+    let options = SimplifyOptions::new().with_input_shapes(input_shapes.clone());
+
+    assert_eq!(options.input_shapes, Some(input_shapes));
+}
+
+#[test]
+fn test_simplify_bytes_with_report_on_invalid_input() {
+    let invalid_model = b"not a valid onnx model";
+
+    onnxsim::init_env();
+    let result = simplify_bytes_with_report(invalid_model, SimplifyOptions::default());
+
+    assert!(result.is_err());
+    match result {
+        Err(onnxsim::OnnxSimError::ParseFailed(_)) => (),
+        _ => panic!("Expected ParseFailed error"),
+    }
+}
+
+#[test]
+fn test_simplify_options_with_validation() {
+    let options = SimplifyOptions::new().with_validation(8, 1e-3, 1e-5);
+
+    assert_eq!(
+        options.validation,
+        Some(onnxsim::ValidationConfig {
+            num_samples: 8,
+            rtol: 1e-3,
+            atol: 1e-5,
+        })
+    );
+}
+
+#[test]
+fn test_simplify_options_external_data_disabled_by_default() {
+    let options = SimplifyOptions::new();
+
+    assert_eq!(options.external_data_threshold, None);
+}
+
+#[test]
+fn test_simplify_options_with_external_data() {
+    let options = SimplifyOptions::new()
+        .with_external_data_threshold(1024 * 1024)
+        .with_external_data_dir("/tmp/weights");
+
+    assert_eq!(options.external_data_threshold, Some(1024 * 1024));
+    assert_eq!(
+        options.external_data_dir,
+        Some(std::path::PathBuf::from("/tmp/weights"))
+    );
+}
+
+#[test]
+fn test_simplify_bytes_with_unknown_input_shape_name() {
+    // A minimal serialized ModelProto whose graph declares a single input
+    // named "input" (field 7 = graph, field 11 = graph.input, field 1 =
+    // ValueInfoProto.name), just enough for Rust-side name validation to
+    // run before the bytes ever reach the FFI boundary.
+    let model_bytes: &[u8] = &[
+        0x3a, 0x09, // graph (field 7), length 9
+        0x5a, 0x07, // input (field 11), length 7
+        0x0a, 0x05, b'i', b'n', b'p', b'u', b't', // name (field 1) = "input"
+    ];
+
+    let mut input_shapes = HashMap::new();
+    input_shapes.insert("not_an_input".to_string(), vec![1, 3, 224, 224]);
+
+    onnxsim::init_env();
+    let result = simplify_bytes(
+        model_bytes,
+        SimplifyOptions::new().with_input_shapes(input_shapes),
+    );
+
+    match result {
+        Err(onnxsim::OnnxSimError::InvalidArgument(_)) => (),
+        other => panic!("Expected InvalidArgument error, got {other:?}"),
+    }
+}
+
 #[test]
 fn test_simplify_file_with_nonexistent_input() {
     let temp_dir = std::env::temp_dir();